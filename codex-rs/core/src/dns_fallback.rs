@@ -3,21 +3,110 @@ use hickory_resolver::TokioResolver;
 use hickory_resolver::config::NameServerConfig;
 use hickory_resolver::config::ResolverConfig;
 use hickory_resolver::config::ResolverOpts;
+use hickory_resolver::config::ServerOrderingStrategy;
 use hickory_resolver::name_server::TokioConnectionProvider;
+use hickory_resolver::proto::rr::Name as DnsName;
 use hickory_resolver::proto::xfer::Protocol;
 use reqwest::dns::Addrs;
 use reqwest::dns::Name;
 use reqwest::dns::Resolve;
 use std::future::Future;
 use std::net::IpAddr;
+use std::net::Ipv4Addr;
 use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::pin::Pin;
 use std::sync::Arc;
+use std::time::Duration;
+
+/// A well-known public DNS provider: the IPs to dial plus the TLS server name
+/// used for certificate validation when dialed over DoT/DoQ/DoH. The `name`
+/// is looked up both for `CODEX_DNS_RESOLVER` (encrypted upstreams) and
+/// `CODEX_DNS_FALLBACK` (plaintext fallback upstreams).
+struct KnownResolver {
+    name: &'static str,
+    ips: &'static [IpAddr],
+    tls_dns_name: &'static str,
+}
+
+const KNOWN_RESOLVERS: &[KnownResolver] = &[
+    KnownResolver {
+        name: "cloudflare",
+        ips: &[
+            IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)),
+            IpAddr::V4(Ipv4Addr::new(1, 0, 0, 1)),
+        ],
+        tls_dns_name: "one.one.one.one",
+    },
+    KnownResolver {
+        name: "google",
+        ips: &[
+            IpAddr::V4(Ipv4Addr::new(8, 8, 8, 8)),
+            IpAddr::V4(Ipv4Addr::new(8, 8, 4, 4)),
+        ],
+        tls_dns_name: "dns.google",
+    },
+    KnownResolver {
+        name: "quad9",
+        ips: &[
+            IpAddr::V4(Ipv4Addr::new(9, 9, 9, 9)),
+            IpAddr::V4(Ipv4Addr::new(149, 112, 112, 112)),
+        ],
+        tls_dns_name: "dns.quad9.net",
+    },
+];
+
+fn lookup_known_resolver(name: &str) -> Option<&'static KnownResolver> {
+    KNOWN_RESOLVERS
+        .iter()
+        .find(|resolver| resolver.name.eq_ignore_ascii_case(name))
+}
+
+/// The encrypted-DNS transport requested via `CODEX_DNS_PROTOCOL`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SecureDnsProtocol {
+    /// DNS-over-TLS.
+    Dot,
+    /// DNS-over-HTTPS.
+    Doh,
+    /// DNS-over-QUIC.
+    Doq,
+}
+
+impl SecureDnsProtocol {
+    fn from_env_str(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "dot" => Some(Self::Dot),
+            "doh" => Some(Self::Doh),
+            "doq" => Some(Self::Doq),
+            _ => None,
+        }
+    }
+
+    fn hickory_protocol(self) -> Protocol {
+        match self {
+            Self::Dot => Protocol::Tls,
+            Self::Doh => Protocol::Https,
+            Self::Doq => Protocol::Quic,
+        }
+    }
+
+    fn default_port(self) -> u16 {
+        match self {
+            Self::Dot | Self::Doq => 853,
+            Self::Doh => 443,
+        }
+    }
+}
 
 #[derive(Clone)]
 pub struct TermuxResolver {
     resolver: Arc<TokioResolver>,
+    /// A plaintext resolver to retry against when `resolver` is a secure
+    /// (DoH/DoT/DoQ) upstream and the encrypted handshake fails at runtime
+    /// (blocked port, TLS failure). `None` when `resolver` is already
+    /// plaintext, since there is nothing to fall back to.
+    fallback: Option<Arc<TokioResolver>>,
 }
 
 pub fn should_install_termux_resolver() -> bool {
@@ -30,15 +119,49 @@ pub fn should_install_termux_resolver() -> bool {
 
 impl TermuxResolver {
     pub fn new() -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
-        let (config, options) = resolver_config_and_options();
-        let mut builder = Resolver::builder_with_config(config, TokioConnectionProvider::default());
-        *builder.options_mut() = options;
-        let resolver = builder.build();
+        let (plaintext_config, options) = plaintext_resolver_config_and_options();
+        let secure_config = secure_resolver_config();
+
+        let (config, fallback) = match secure_config {
+            // Keep the plaintext config's ndots/timeout/attempts/rotate/edns0
+            // and search/domain semantics, swapping in only the secure name
+            // servers, so unqualified-name lookups still match `getaddrinfo`.
+            Some(secure_config) => {
+                let config = ResolverConfig::from_parts(
+                    plaintext_config.domain().cloned(),
+                    plaintext_config.search().to_vec(),
+                    secure_config.name_servers().to_vec(),
+                );
+                let fallback = build_resolver(plaintext_config, options.clone());
+                (config, Some(Arc::new(fallback)))
+            }
+            None => (plaintext_config, None),
+        };
+        let resolver = build_resolver(config, options);
 
         Ok(Self {
             resolver: Arc::new(resolver),
+            fallback,
         })
     }
+
+    /// Performs a one-shot lookup without requiring an ambient Tokio runtime,
+    /// for setup code and diagnostics (e.g. connectivity self-checks) that run
+    /// outside an async context. Spins up a small current-thread runtime
+    /// internally to drive the same `TokioResolver` used by [`Resolve`],
+    /// retrying against the plaintext fallback resolver on failure.
+    pub fn resolve_blocking(
+        &self,
+        name: &str,
+    ) -> Result<Vec<SocketAddr>, Box<dyn std::error::Error + Send + Sync>> {
+        let resolver = self.resolver.clone();
+        let fallback = self.fallback.clone();
+        let name = name.to_string();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        runtime.block_on(async move { lookup_with_fallback(&resolver, fallback.as_deref(), &name).await })
+    }
 }
 
 impl Resolve for TermuxResolver {
@@ -48,29 +171,138 @@ impl Resolve for TermuxResolver {
     ) -> Pin<Box<dyn Future<Output = Result<Addrs, Box<dyn std::error::Error + Send + Sync>>> + Send>>
     {
         let resolver = self.resolver.clone();
+        let fallback = self.fallback.clone();
         Box::pin(async move {
-            let lookup = resolver.lookup_ip(name.as_str()).await?;
-            let addrs: Addrs = Box::new(lookup.into_iter().map(|ip| SocketAddr::new(ip, 0)));
-            Ok(addrs)
+            let addrs = lookup_with_fallback(&resolver, fallback.as_deref(), name.as_str()).await?;
+            Ok(Box::new(addrs.into_iter()) as Addrs)
         })
     }
 }
 
-fn resolver_config_and_options() -> (ResolverConfig, ResolverOpts) {
+/// Looks up `name` against `resolver`, retrying against `fallback` (the
+/// plaintext resolver) if the primary lookup fails. Without a `fallback`, the
+/// primary's error is returned as-is.
+async fn lookup_with_fallback(
+    resolver: &TokioResolver,
+    fallback: Option<&TokioResolver>,
+    name: &str,
+) -> Result<Vec<SocketAddr>, Box<dyn std::error::Error + Send + Sync>> {
+    let lookup = match resolver.lookup_ip(name).await {
+        Ok(lookup) => lookup,
+        Err(primary_err) => match fallback {
+            Some(fallback) => {
+                tracing::warn!(
+                    name,
+                    error = %primary_err,
+                    "secure DNS lookup failed, falling back to plaintext DNS"
+                );
+                fallback.lookup_ip(name).await?
+            }
+            None => return Err(Box::new(primary_err)),
+        },
+    };
+    Ok(lookup.into_iter().map(|ip| SocketAddr::new(ip, 0)).collect())
+}
+
+fn build_resolver(config: ResolverConfig, options: ResolverOpts) -> TokioResolver {
+    let mut builder = Resolver::builder_with_config(config, TokioConnectionProvider::default());
+    *builder.options_mut() = options;
+    builder.build()
+}
+
+/// The plaintext upstream chain: system config, then PREFIX's resolv.conf,
+/// then `CODEX_DNS_FALLBACK`, then `ResolverConfig::google()`.
+fn plaintext_resolver_config_and_options() -> (ResolverConfig, ResolverOpts) {
     if let Ok((config, options)) = hickory_resolver::system_conf::read_system_conf() {
         return (config, options);
     }
 
-    let mut config = ResolverConfig::new();
     if let Ok(content) = read_prefix_resolv_conf() {
-        add_nameservers_from_resolv_conf(&content, &mut config);
+        let (config, opts) = add_nameservers_from_resolv_conf(&content);
+        if !config.name_servers().is_empty() {
+            return (config, opts);
+        }
     }
 
-    if config.name_servers().is_empty() {
-        return (ResolverConfig::google(), ResolverOpts::default());
+    if let Some(config) = fallback_resolver_config() {
+        return (config, ResolverOpts::default());
     }
 
-    (config, ResolverOpts::default())
+    (ResolverConfig::google(), ResolverOpts::default())
+}
+
+/// Builds a plaintext `ResolverConfig` from the ordered, comma-separated
+/// `CODEX_DNS_FALLBACK` list (e.g. `cloudflare,quad9,google`), where each
+/// entry is either a known provider name or a raw IP address. Returns `None`
+/// if the variable is unset or yields no usable servers, so the caller keeps
+/// defaulting to `ResolverConfig::google()`.
+fn fallback_resolver_config() -> Option<ResolverConfig> {
+    let raw = std::env::var("CODEX_DNS_FALLBACK").ok()?;
+    fallback_resolver_config_from_list(&raw)
+}
+
+fn fallback_resolver_config_from_list(raw: &str) -> Option<ResolverConfig> {
+    let mut config = ResolverConfig::new();
+
+    for entry in raw.split(',') {
+        let entry = entry.trim();
+        if entry.is_empty() {
+            continue;
+        }
+        if let Some(provider) = lookup_known_resolver(entry) {
+            for ip in provider.ips {
+                add_nameserver_for_ip(&mut config, SocketAddr::new(*ip, 53));
+            }
+        } else if let Ok(ip) = entry.parse::<IpAddr>() {
+            add_nameserver_for_ip(&mut config, SocketAddr::new(ip, 53));
+        }
+    }
+
+    (!config.name_servers().is_empty()).then_some(config)
+}
+
+/// Builds a `ResolverConfig` from `CODEX_DNS_PROTOCOL` (`doh`/`dot`/`doq`) and
+/// `CODEX_DNS_RESOLVER` (defaults to `cloudflare`), or returns `None` so the
+/// caller can fall back to plaintext if `CODEX_DNS_PROTOCOL` is unset or
+/// either value is unrecognized.
+fn secure_resolver_config() -> Option<ResolverConfig> {
+    let raw_protocol = std::env::var("CODEX_DNS_PROTOCOL").ok()?;
+    let Some(protocol) = SecureDnsProtocol::from_env_str(&raw_protocol) else {
+        tracing::warn!(
+            protocol = %raw_protocol,
+            "unrecognized CODEX_DNS_PROTOCOL, falling back to plaintext DNS"
+        );
+        return None;
+    };
+    let resolver_name =
+        std::env::var("CODEX_DNS_RESOLVER").unwrap_or_else(|_| "cloudflare".to_string());
+    let Some(resolver) = lookup_known_resolver(&resolver_name) else {
+        tracing::warn!(
+            resolver = %resolver_name,
+            "unrecognized CODEX_DNS_RESOLVER, falling back to plaintext DNS"
+        );
+        return None;
+    };
+
+    let mut config = ResolverConfig::new();
+    for ip in resolver.ips {
+        add_secure_nameserver(&mut config, *ip, protocol, resolver.tls_dns_name);
+    }
+    Some(config)
+}
+
+fn add_secure_nameserver(
+    config: &mut ResolverConfig,
+    ip: IpAddr,
+    protocol: SecureDnsProtocol,
+    tls_dns_name: &str,
+) {
+    let mut server = NameServerConfig::new(
+        SocketAddr::new(ip, protocol.default_port()),
+        protocol.hickory_protocol(),
+    );
+    server.tls_dns_name = Some(tls_dns_name.to_string());
+    config.add_name_server(server);
 }
 
 fn read_prefix_resolv_conf() -> Result<String, std::io::Error> {
@@ -79,30 +311,151 @@ fn read_prefix_resolv_conf() -> Result<String, std::io::Error> {
     std::fs::read_to_string(path)
 }
 
-fn add_nameservers_from_resolv_conf(content: &str, config: &mut ResolverConfig) {
+/// Parses a resolv.conf-style `content` blob into a `ResolverConfig` plus the
+/// `ResolverOpts` derived from its `options` line, so unqualified-name lookups
+/// behave the way `getaddrinfo` would for the same file.
+fn add_nameservers_from_resolv_conf(content: &str) -> (ResolverConfig, ResolverOpts) {
+    let mut config = ResolverConfig::new();
+    let mut opts = ResolverOpts::default();
+    // `domain` and `search` are mutually exclusive in resolv.conf: whichever
+    // directive appears last wins and replaces the other outright.
+    let mut search_directive: Option<SearchDirective> = None;
+
     for line in content.lines() {
         let line = line.trim();
-        if line.starts_with("nameserver ")
-            && let Some(ip) = line
-                .trim_start_matches("nameserver ")
-                .trim()
-                .parse::<IpAddr>()
-                .ok()
-        {
-            add_nameserver_for_ip(config, ip);
+        // resolv.conf directives may be separated from their value by any
+        // run of whitespace (including tabs), not just a single space.
+        let Some((keyword, rest)) = line.split_once(char::is_whitespace) else {
+            continue;
+        };
+        let rest = rest.trim_start();
+        match keyword {
+            "nameserver" => {
+                if let Some(socket_addr) = parse_nameserver_value(rest) {
+                    add_nameserver_for_ip(&mut config, socket_addr);
+                }
+            }
+            "options" => apply_resolv_options(rest, &mut opts),
+            "search" => {
+                let parsed: Vec<DnsName> = rest
+                    .split_whitespace()
+                    .filter_map(|domain| DnsName::from_ascii(domain).ok())
+                    .collect();
+                if !parsed.is_empty() {
+                    search_directive = Some(SearchDirective::Search(parsed));
+                }
+            }
+            "domain" => {
+                if let Ok(name) = DnsName::from_ascii(rest.trim()) {
+                    search_directive = Some(SearchDirective::Domain(name));
+                }
+            }
+            _ => {}
         }
     }
+
+    if let Some(directive) = search_directive {
+        let (domain, search) = match directive {
+            SearchDirective::Domain(name) => (Some(name.clone()), vec![name]),
+            SearchDirective::Search(names) => (None, names),
+        };
+        config = ResolverConfig::from_parts(domain, search, config.name_servers().to_vec());
+    }
+
+    (config, opts)
+}
+
+/// The most recently seen `domain`/`search` line in a resolv.conf file; the
+/// two directives are mutually exclusive, so only the last one is kept.
+enum SearchDirective {
+    Domain(DnsName),
+    Search(Vec<DnsName>),
+}
+
+/// Applies a resolv.conf `options` line (e.g. `ndots:2 timeout:5 rotate`) to
+/// `opts`, ignoring unrecognized or malformed entries rather than aborting.
+fn apply_resolv_options(rest: &str, opts: &mut ResolverOpts) {
+    for token in rest.split_whitespace() {
+        match token.split_once(':') {
+            Some(("ndots", value)) => {
+                if let Ok(value) = value.parse() {
+                    opts.ndots = value;
+                }
+            }
+            Some(("timeout", value)) => {
+                if let Ok(value) = value.parse() {
+                    opts.timeout = Duration::from_secs(value);
+                }
+            }
+            Some(("attempts", value)) => {
+                if let Ok(value) = value.parse() {
+                    opts.attempts = value;
+                }
+            }
+            None if token == "rotate" => {
+                opts.server_ordering_strategy = ServerOrderingStrategy::RoundRobin;
+            }
+            None if token == "edns0" => opts.edns0 = true,
+            _ => {}
+        }
+    }
+}
+
+fn add_nameserver_for_ip(config: &mut ResolverConfig, socket_addr: SocketAddr) {
+    config.add_name_server(NameServerConfig::new(socket_addr, Protocol::Udp));
+    config.add_name_server(NameServerConfig::new(socket_addr, Protocol::Tcp));
+}
+
+/// Parses a `nameserver` value, accepting `IP`, `IP#port`, `IP:port` (IPv4
+/// only, since bare IPv6 literals are themselves colon-separated) and a
+/// link-local IPv6 `%zone` suffix (e.g. `fe80::1%wlan0`), resolved to a scope
+/// id via `if_nametoindex` so resolution over that interface actually works.
+fn parse_nameserver_value(value: &str) -> Option<SocketAddr> {
+    let value = value.trim();
+
+    let (addr_and_zone, port) = match value.rsplit_once('#') {
+        Some((left, port_str)) => (left, port_str.parse::<u16>().ok()),
+        None => (value, None),
+    };
+
+    let (addr_str, zone) = match addr_and_zone.split_once('%') {
+        Some((addr, zone)) => (addr, Some(zone)),
+        None => (addr_and_zone, None),
+    };
+
+    let (addr_str, port) = if port.is_none() {
+        match addr_str.rsplit_once(':') {
+            Some((ip_part, port_str)) if ip_part.parse::<Ipv4Addr>().is_ok() => {
+                (ip_part, port_str.parse::<u16>().ok())
+            }
+            _ => (addr_str, None),
+        }
+    } else {
+        (addr_str, port)
+    };
+
+    let ip: IpAddr = addr_str.parse().ok()?;
+    let port = port.unwrap_or(53);
+
+    Some(match ip {
+        IpAddr::V6(v6) => {
+            let scope_id = zone.and_then(zone_to_scope_id).unwrap_or(0);
+            SocketAddr::from(std::net::SocketAddrV6::new(v6, port, 0, scope_id))
+        }
+        IpAddr::V4(v4) => SocketAddr::from(std::net::SocketAddrV4::new(v4, port)),
+    })
+}
+
+#[cfg(unix)]
+fn zone_to_scope_id(zone: &str) -> Option<u32> {
+    let zone = std::ffi::CString::new(zone).ok()?;
+    let index = unsafe { libc::if_nametoindex(zone.as_ptr()) };
+    (index != 0).then_some(index)
 }
 
-fn add_nameserver_for_ip(config: &mut ResolverConfig, ip: IpAddr) {
-    config.add_name_server(NameServerConfig::new(
-        SocketAddr::new(ip, 53),
-        Protocol::Udp,
-    ));
-    config.add_name_server(NameServerConfig::new(
-        SocketAddr::new(ip, 53),
-        Protocol::Tcp,
-    ));
+#[cfg(not(unix))]
+fn zone_to_scope_id(_zone: &str) -> Option<u32> {
+    None
 }
 
 fn should_install_termux_resolver_with(
@@ -128,11 +481,50 @@ mod tests {
         assert!(resolver.is_ok());
     }
 
+    #[test]
+    fn test_resolve_blocking_runs_without_ambient_runtime() {
+        let mut broken_config = ResolverConfig::new();
+        add_nameserver_for_ip(
+            &mut broken_config,
+            SocketAddr::new("203.0.113.1".parse().unwrap(), 53),
+        );
+        let mut opts = ResolverOpts::default();
+        opts.timeout = Duration::from_millis(50);
+        opts.attempts = 1;
+        let resolver = TermuxResolver {
+            resolver: Arc::new(build_resolver(broken_config, opts)),
+            fallback: None,
+        };
+
+        // No ambient Tokio runtime is running in this test; `resolve_blocking`
+        // must spin up its own and still return (as an error, since the
+        // configured server is unreachable), rather than panicking.
+        let result = resolver.resolve_blocking("example.com");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_lookup_with_fallback_without_fallback_returns_primary_error() {
+        let mut broken_config = ResolverConfig::new();
+        add_nameserver_for_ip(
+            &mut broken_config,
+            SocketAddr::new("203.0.113.1".parse().unwrap(), 53),
+        );
+        let mut opts = ResolverOpts::default();
+        opts.timeout = Duration::from_millis(50);
+        opts.attempts = 1;
+        let broken = build_resolver(broken_config, opts);
+
+        let runtime = tokio::runtime::Runtime::new().expect("runtime builds");
+        let result = runtime.block_on(lookup_with_fallback(&broken, None, "example.com"));
+        assert!(result.is_err());
+    }
+
+
     #[test]
     fn test_parse_resolv_conf_contents() {
         let content = "nameserver 1.1.1.1\nnameserver 8.8.8.8\n# comment\n  nameserver 9.9.9.9  ";
-        let mut config = ResolverConfig::new();
-        add_nameservers_from_resolv_conf(content, &mut config);
+        let (config, _opts) = add_nameservers_from_resolv_conf(content);
         assert_eq!(config.name_servers().len(), 6);
         assert_eq!(
             config.name_servers()[0].socket_addr.ip().to_string(),
@@ -160,10 +552,61 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_resolv_conf_tolerates_tab_separated_directives() {
+        let content = "nameserver\t1.1.1.1\noptions\tndots:2\tattempts:3\nsearch\texample.com\tcorp.example.com";
+        let (config, opts) = add_nameservers_from_resolv_conf(content);
+        assert_eq!(config.name_servers().len(), 2);
+        assert_eq!(opts.ndots, 2);
+        assert_eq!(opts.attempts, 3);
+        assert_eq!(config.search().len(), 2);
+    }
+
+    #[test]
+    fn test_parse_resolv_conf_options() {
+        let content = "nameserver 1.1.1.1\noptions ndots:2 timeout:5 attempts:3 rotate edns0 bogus:1";
+        let (_config, opts) = add_nameservers_from_resolv_conf(content);
+        assert_eq!(opts.ndots, 2);
+        assert_eq!(opts.timeout, Duration::from_secs(5));
+        assert_eq!(opts.attempts, 3);
+        assert_eq!(
+            opts.server_ordering_strategy,
+            ServerOrderingStrategy::RoundRobin
+        );
+        assert!(opts.edns0);
+    }
+
+    #[test]
+    fn test_parse_resolv_conf_domain_overrides_earlier_search() {
+        let content = "nameserver 1.1.1.1\nsearch example.com corp.example.com\ndomain example.com";
+        let (config, _opts) = add_nameservers_from_resolv_conf(content);
+        assert_eq!(
+            config.domain().map(|name| name.to_string()),
+            Some("example.com".to_string())
+        );
+        assert_eq!(config.search().len(), 1);
+    }
+
+    #[test]
+    fn test_parse_resolv_conf_search_overrides_earlier_domain() {
+        let content = "nameserver 1.1.1.1\ndomain example.com\nsearch foo.example.com bar.example.com";
+        let (config, _opts) = add_nameservers_from_resolv_conf(content);
+        assert_eq!(config.domain(), None);
+        assert_eq!(config.search().len(), 2);
+    }
+
+    #[test]
+    fn test_parse_resolv_conf_ignores_malformed_options() {
+        let content = "nameserver 1.1.1.1\noptions ndots:notanumber timeout:5";
+        let (_config, opts) = add_nameservers_from_resolv_conf(content);
+        assert_eq!(opts.ndots, ResolverOpts::default().ndots);
+        assert_eq!(opts.timeout, Duration::from_secs(5));
+    }
+
     #[test]
     fn test_add_nameserver_for_ip_adds_udp_and_tcp() {
         let mut config = ResolverConfig::new();
-        add_nameserver_for_ip(&mut config, "1.1.1.1".parse().expect("valid ip"));
+        add_nameserver_for_ip(&mut config, SocketAddr::new("1.1.1.1".parse().unwrap(), 53));
         let protocols = config
             .name_servers()
             .iter()
@@ -172,6 +615,36 @@ mod tests {
         assert_eq!(protocols, vec![Protocol::Udp, Protocol::Tcp]);
     }
 
+    #[test]
+    fn test_parse_nameserver_value_plain_ipv4() {
+        let addr = parse_nameserver_value("1.1.1.1").expect("parses");
+        assert_eq!(addr, "1.1.1.1:53".parse().unwrap());
+    }
+
+    #[test]
+    fn test_parse_nameserver_value_ipv4_custom_port() {
+        assert_eq!(
+            parse_nameserver_value("1.1.1.1#5353"),
+            Some("1.1.1.1:5353".parse().unwrap())
+        );
+        assert_eq!(
+            parse_nameserver_value("1.1.1.1:5353"),
+            Some("1.1.1.1:5353".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_parse_nameserver_value_scoped_ipv6() {
+        let addr = parse_nameserver_value("fe80::1%lo").expect("parses");
+        match addr {
+            SocketAddr::V6(v6) => {
+                assert_eq!(v6.ip().to_string(), "fe80::1");
+                assert_eq!(v6.port(), 53);
+            }
+            SocketAddr::V4(_) => panic!("expected an IPv6 address"),
+        }
+    }
+
     #[test]
     fn should_install_termux_resolver_detects_signals() {
         assert_eq!(
@@ -204,4 +677,62 @@ mod tests {
         );
         assert_eq!(should_install_termux_resolver_with(true, None, None), true);
     }
+
+    #[test]
+    fn test_secure_dns_protocol_from_env_str() {
+        assert_eq!(
+            SecureDnsProtocol::from_env_str("DoH"),
+            Some(SecureDnsProtocol::Doh)
+        );
+        assert_eq!(
+            SecureDnsProtocol::from_env_str("dot"),
+            Some(SecureDnsProtocol::Dot)
+        );
+        assert_eq!(
+            SecureDnsProtocol::from_env_str("doq"),
+            Some(SecureDnsProtocol::Doq)
+        );
+        assert_eq!(SecureDnsProtocol::from_env_str("quic"), None);
+    }
+
+    #[test]
+    fn test_add_secure_nameserver_sets_protocol_and_tls_name() {
+        let mut config = ResolverConfig::new();
+        add_secure_nameserver(
+            &mut config,
+            "1.1.1.1".parse().expect("valid ip"),
+            SecureDnsProtocol::Dot,
+            "one.one.one.one",
+        );
+        let server = &config.name_servers()[0];
+        assert_eq!(server.protocol, Protocol::Tls);
+        assert_eq!(server.socket_addr.port(), 853);
+        assert_eq!(server.tls_dns_name.as_deref(), Some("one.one.one.one"));
+    }
+
+    #[test]
+    fn test_lookup_known_resolver_is_case_insensitive() {
+        assert!(lookup_known_resolver("Cloudflare").is_some());
+        assert!(lookup_known_resolver("nonexistent").is_none());
+    }
+
+    #[test]
+    fn test_fallback_resolver_config_from_list_mixes_providers_and_ips() {
+        let config =
+            fallback_resolver_config_from_list("cloudflare,quad9,203.0.113.1").expect("some");
+        let ips = config
+            .name_servers()
+            .iter()
+            .map(|server| server.socket_addr.ip().to_string())
+            .collect::<Vec<_>>();
+        assert!(ips.contains(&"1.1.1.1".to_string()));
+        assert!(ips.contains(&"9.9.9.9".to_string()));
+        assert!(ips.contains(&"203.0.113.1".to_string()));
+    }
+
+    #[test]
+    fn test_fallback_resolver_config_from_list_empty_is_none() {
+        assert!(fallback_resolver_config_from_list("").is_none());
+        assert!(fallback_resolver_config_from_list("not-a-provider").is_none());
+    }
 }